@@ -0,0 +1,678 @@
+// Smoldot
+// Copyright (C) 2019-2022  Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Chain specifications ("chain spec") found in Substrate-based chains, and required to
+//! synchronize a chain.
+//!
+//! The items of this module permit parsing chain specs JSON files and retrieving the
+//! information they contain.
+
+mod light_sync_state;
+mod structs;
+
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use core::{fmt, str};
+use hashbrown::HashMap;
+use serde::Deserialize;
+
+pub use light_sync_state::LightSyncState;
+pub use structs::{ChainType, ChildRawStorage, HashHexString, HexString};
+
+/// A decoded chain specification.
+pub struct ChainSpec {
+    client_spec: structs::ClientSpec,
+}
+
+impl ChainSpec {
+    /// Parse the given JSON content into a [`ChainSpec`].
+    pub fn from_json_bytes(json: impl AsRef<[u8]>) -> Result<Self, ParseError> {
+        let client_spec: structs::ClientSpec =
+            serde_json::from_slice(json.as_ref()).map_err(ParseError)?;
+        Ok(ChainSpec { client_spec })
+    }
+
+    /// Serializes this [`ChainSpec`] back into its JSON representation.
+    pub fn as_json_bytes(&self) -> String {
+        serde_json::to_string_pretty(&self.client_spec)
+            .unwrap_or_else(|_| unreachable!("ClientSpec always serializes successfully"))
+    }
+
+    /// Human-readable name of the chain.
+    pub fn name(&self) -> &str {
+        &self.client_spec.name
+    }
+
+    /// Identifier of the chain.
+    pub fn id(&self) -> &str {
+        &self.client_spec.id
+    }
+
+    /// List of node addresses to try to connect to in order to reach the peer-to-peer network.
+    pub fn boot_nodes(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.client_spec.boot_nodes.iter().map(String::as_str)
+    }
+
+    /// Optional protocol id prefixed to the network messages, as a replacement to the hash of
+    /// the genesis block.
+    pub fn protocol_id(&self) -> Option<&str> {
+        self.client_spec.protocol_id.as_deref()
+    }
+
+    /// Returns the runtime code that must be used in place of the on-chain `:code` for every
+    /// descendant of the block with the given number, for as long as the
+    /// `CoreVersionRef::spec_version` of the runtime doesn't change compared to the substitute.
+    ///
+    /// See the documentation of [`structs::ClientSpec::code_substitutes`] for more information.
+    pub fn code_substitutes(&self) -> BTreeMap<u64, &[u8]> {
+        self.client_spec
+            .code_substitutes
+            .iter()
+            .map(|(number, code)| (number.0, &code.0[..]))
+            .collect()
+    }
+
+    /// List of block hashes that must unconditionally be rejected during the sync process.
+    pub fn bad_blocks_hashes(&self) -> impl Iterator<Item = &[u8; 32]> {
+        self.client_spec
+            .bad_blocks
+            .iter()
+            .flatten()
+            .map(|hash| &hash.0)
+    }
+
+    /// List of `(height, hash)` tuples. The block at the given height must have the given hash,
+    /// or must be rejected.
+    pub fn known_forks(&self) -> impl Iterator<Item = (u64, &[u8; 32])> {
+        self.client_spec
+            .fork_blocks
+            .iter()
+            .flatten()
+            .map(|(height, hash)| (*height, &hash.0))
+    }
+
+    /// Checks whether a block received during the sync process (for example through a block
+    /// announce or a block request response) must be rejected because of [`ChainSpec::bad_blocks_hashes`]
+    /// or [`ChainSpec::known_forks`].
+    ///
+    /// This should be called by the block-announces and block-import paths for every header
+    /// before further processing it.
+    pub fn check_block_is_allowed(
+        &self,
+        height: u64,
+        hash: &[u8; 32],
+    ) -> Result<(), BadBlockError> {
+        if self
+            .client_spec
+            .bad_blocks
+            .as_ref()
+            .map_or(false, |set| set.contains(&HashHexString(*hash)))
+        {
+            return Err(BadBlockError::Blacklisted);
+        }
+
+        if let Some((_, expected_hash)) = self.known_forks().find(|(h, _)| *h == height) {
+            if expected_hash != hash {
+                return Err(BadBlockError::ForkMismatch);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Error returned by [`ChainSpec::check_block_is_allowed`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BadBlockError {
+    /// The block hash is explicitly listed in the chain spec's `badBlocks`.
+    Blacklisted,
+    /// The block is at a height listed in the chain spec's `forkBlocks`, but its hash doesn't
+    /// match the one mandated by the chain spec.
+    ForkMismatch,
+}
+
+impl fmt::Display for BadBlockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BadBlockError::Blacklisted => write!(f, "block is explicitly marked as bad"),
+            BadBlockError::ForkMismatch => {
+                write!(f, "block hash doesn't match the hash mandated by a fork block")
+            }
+        }
+    }
+}
+
+/// Error potentially returned by [`ChainSpec::from_json_bytes`].
+#[derive(Debug)]
+pub struct ParseError(serde_json::Error);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Builder allowing to construct a [`ChainSpec`] programmatically, as an alternative to parsing
+/// one from JSON.
+pub struct ChainSpecBuilder {
+    name: String,
+    id: String,
+    chain_type: ChainType,
+    boot_nodes: Vec<String>,
+    protocol_id: Option<String>,
+    fork_id: Option<String>,
+    top: BTreeMap<HexString, HexString>,
+    children_default: BTreeMap<HexString, ChildRawStorage>,
+    state_root_hash: Option<HashHexString>,
+    parachain: Option<(String, u32)>,
+}
+
+impl ChainSpecBuilder {
+    /// Initializes a new builder for a chain spec with an empty raw genesis storage.
+    pub fn new(name: impl Into<String>, id: impl Into<String>) -> Self {
+        ChainSpecBuilder {
+            name: name.into(),
+            id: id.into(),
+            chain_type: ChainType::Live,
+            boot_nodes: Vec::new(),
+            protocol_id: None,
+            fork_id: None,
+            top: BTreeMap::new(),
+            children_default: BTreeMap::new(),
+            state_root_hash: None,
+            parachain: None,
+        }
+    }
+
+    /// Sets the [`ChainType`] of the chain. Defaults to [`ChainType::Live`].
+    pub fn chain_type(mut self, chain_type: ChainType) -> Self {
+        self.chain_type = chain_type;
+        self
+    }
+
+    /// Adds a boot node address.
+    pub fn boot_node(mut self, address: impl Into<String>) -> Self {
+        self.boot_nodes.push(address.into());
+        self
+    }
+
+    /// Sets the protocol id. See [`ChainSpec::protocol_id`].
+    pub fn protocol_id(mut self, protocol_id: impl Into<String>) -> Self {
+        self.protocol_id = Some(protocol_id.into());
+        self
+    }
+
+    /// Sets the fork id.
+    pub fn fork_id(mut self, fork_id: impl Into<String>) -> Self {
+        self.fork_id = Some(fork_id.into());
+        self
+    }
+
+    /// Inserts an entry in the genesis storage's top trie.
+    ///
+    /// This entry is only used if the resulting [`ChainSpec`] ends up using a raw genesis, i.e.
+    /// [`ChainSpecBuilder::genesis_state_root_hash`] hasn't been called. See the documentation
+    /// of [`ChainSpecBuilder::genesis_state_root_hash`] for more information.
+    pub fn insert_top_storage(mut self, key: Vec<u8>, value: Vec<u8>) -> Self {
+        self.top.insert(HexString(key), HexString(value));
+        self
+    }
+
+    /// Inserts an entry in a child trie of the genesis storage.
+    ///
+    /// This entry is only used if the resulting [`ChainSpec`] ends up using a raw genesis, i.e.
+    /// [`ChainSpecBuilder::genesis_state_root_hash`] hasn't been called. See the documentation
+    /// of [`ChainSpecBuilder::genesis_state_root_hash`] for more information.
+    pub fn insert_child_default_storage(
+        mut self,
+        child_trie_key: Vec<u8>,
+        child_info: Vec<u8>,
+        child_type: u32,
+    ) -> Self {
+        self.children_default.insert(
+            HexString(child_trie_key),
+            ChildRawStorage {
+                child_info,
+                child_type,
+            },
+        );
+        self
+    }
+
+    /// Uses a pre-computed state trie root hash as the genesis, instead of a raw genesis
+    /// storage.
+    ///
+    /// If this is called, [`ChainSpecBuilder::build`] uses this hash rather than the entries
+    /// inserted through [`ChainSpecBuilder::insert_top_storage`] and
+    /// [`ChainSpecBuilder::insert_child_default_storage`], which are kept in the builder but
+    /// left out of the resulting [`ChainSpec`].
+    pub fn genesis_state_root_hash(mut self, state_root: [u8; 32]) -> Self {
+        self.state_root_hash = Some(HashHexString(state_root));
+        self
+    }
+
+    /// Marks the chain as a parachain, attaching the identity of its relay chain and its
+    /// parachain id.
+    pub fn parachain(mut self, relay_chain: impl Into<String>, para_id: u32) -> Self {
+        self.parachain = Some((relay_chain.into(), para_id));
+        self
+    }
+
+    /// Finalizes the builder into a [`ChainSpec`].
+    pub fn build(self) -> ChainSpec {
+        let genesis = match self.state_root_hash {
+            Some(hash) => structs::Genesis::StateRootHash(hash),
+            None => structs::Genesis::Raw(structs::RawGenesis {
+                top: self.top,
+                children_default: self.children_default,
+            }),
+        };
+
+        ChainSpec {
+            client_spec: structs::ClientSpec {
+                name: self.name,
+                id: self.id,
+                chain_type: self.chain_type,
+                code_substitutes: HashMap::default(),
+                boot_nodes: self.boot_nodes,
+                telemetry_endpoints: None,
+                protocol_id: self.protocol_id,
+                fork_id: self.fork_id,
+                properties: None,
+                fork_blocks: None,
+                bad_blocks: None,
+                consensus_engine: (),
+                genesis,
+                light_sync_state: None,
+                parachain: self.parachain.map(|(relay_chain, para_id)| {
+                    structs::ChainSpecParachain {
+                        relay_chain,
+                        para_id,
+                    }
+                }),
+            },
+        }
+    }
+}
+
+impl ChainSpec {
+    /// Parses only the `genesis.raw.top` portion of a chain specification JSON document and
+    /// returns an iterator that decodes its entries one at a time.
+    ///
+    /// Contrary to [`ChainSpec::from_json_bytes`], this doesn't materialize the storage into a
+    /// `BTreeMap` and doesn't decode any part of the document other than `genesis.raw.top`,
+    /// which keeps peak memory usage bounded when loading chain specifications whose genesis
+    /// storage is hundreds of megabytes large, for example on memory-constrained targets such
+    /// as `wasm32`.
+    ///
+    /// Returns `Ok(None)` if the chain specification doesn't contain a raw genesis (i.e. it
+    /// uses a `stateRootHash` instead).
+    pub fn genesis_storage_top_trie_entries_from_json_bytes(
+        json: &[u8],
+    ) -> Result<Option<GenesisStorageEntries>, ParseError> {
+        #[derive(Deserialize)]
+        struct Document<'a> {
+            #[serde(borrow)]
+            genesis: DocumentGenesis<'a>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct DocumentGenesis<'a> {
+            #[serde(borrow, default)]
+            raw: Option<DocumentRawGenesis<'a>>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct DocumentRawGenesis<'a> {
+            #[serde(borrow)]
+            top: &'a serde_json::value::RawValue,
+        }
+
+        let json_str = str::from_utf8(json)
+            .map_err(|err| ParseError(<serde_json::Error as serde::de::Error>::custom(err)))?;
+        let document: Document = serde_json::from_str(json_str).map_err(ParseError)?;
+
+        match document.genesis.raw {
+            Some(raw) => Ok(Some(GenesisStorageEntries::new(raw.top.get())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Consumes a [`GenesisStorageEntries`] iterator and folds it into the genesis storage
+    /// trie's state root hash.
+    ///
+    /// [`GenesisStorageEntries`] yields entries in the order they appear in the JSON document,
+    /// which, unlike a `BTreeMap`, is not guaranteed to be sorted by key or free of duplicate
+    /// keys. To compute the same root as [`ChainSpec::from_json_bytes`] would (where the last
+    /// occurrence of a duplicate key wins, as it does when inserting into a `BTreeMap`), this
+    /// sorts and dedupes the entries before hashing them, which means that the whole storage
+    /// is, despite the streaming decoding, still held in memory at once at this stage.
+    ///
+    /// The trie root itself is computed using the same incremental trie-building logic used
+    /// elsewhere in the codebase (see `crate::trie::calculate_root`).
+    pub fn genesis_storage_trie_root(
+        entries: GenesisStorageEntries,
+    ) -> Result<[u8; 32], ParseError> {
+        let sorted_entries = sorted_deduped_entries(entries)?;
+
+        Ok(crate::trie::calculate_root::root_merkle_value(
+            sorted_entries.into_iter(),
+        ))
+    }
+}
+
+/// Decodes every entry of `entries`, sorting them by key and keeping, for duplicate keys, only
+/// the last occurrence in the iteration order. This matches the semantics of deserializing the
+/// same JSON object into a `BTreeMap`.
+fn sorted_deduped_entries(
+    entries: GenesisStorageEntries,
+) -> Result<BTreeMap<Vec<u8>, Vec<u8>>, ParseError> {
+    let mut sorted = BTreeMap::new();
+    for entry in entries {
+        let (key, value) = entry?;
+        sorted.insert(key.0, value.0);
+    }
+    Ok(sorted)
+}
+
+/// Iterator over the entries of the genesis storage's top trie, decoded incrementally from the
+/// raw JSON text of `genesis.raw.top` rather than from a fully materialized `BTreeMap`.
+///
+/// Obtained with [`ChainSpec::genesis_storage_top_trie_entries_from_json_bytes`].
+pub struct GenesisStorageEntries<'a> {
+    /// Bytes of the JSON object not decoded yet, without the leading `{` or trailing `}`.
+    remaining: &'a str,
+    /// `false` once at least one entry has been yielded, at which point a `,` is required
+    /// before the next one, and a trailing `,` followed by the end of the object is an error
+    /// rather than being silently accepted, matching `serde_json`'s own object grammar.
+    first: bool,
+}
+
+impl<'a> GenesisStorageEntries<'a> {
+    fn new(raw_object: &'a str) -> Result<Self, ParseError> {
+        let inner = raw_object
+            .trim()
+            .strip_prefix('{')
+            .and_then(|s| s.strip_suffix('}'))
+            .ok_or_else(|| {
+                ParseError(<serde_json::Error as serde::de::Error>::custom(
+                    "expected a JSON object",
+                ))
+            })?;
+
+        Ok(GenesisStorageEntries {
+            remaining: inner,
+            first: true,
+        })
+    }
+
+    /// Decodes a single `"0x..."` JSON string found at the very start of `input`, and returns
+    /// the decoded bytes alongside the part of `input` that comes after that string.
+    fn decode_hex_json_string(input: &'a str) -> Result<(Vec<u8>, &'a str), ParseError> {
+        let mut stream = serde_json::Deserializer::from_str(input).into_iter::<String>();
+
+        let decoded = stream
+            .next()
+            .ok_or_else(|| {
+                ParseError(<serde_json::Error as serde::de::Error>::custom(
+                    "expected a JSON string",
+                ))
+            })?
+            .map_err(ParseError)?;
+        let consumed = stream.byte_offset();
+
+        let hex_digits = decoded.strip_prefix("0x").ok_or_else(|| {
+            ParseError(<serde_json::Error as serde::de::Error>::custom(
+                "hexadecimal string doesn't start with 0x",
+            ))
+        })?;
+        let bytes = hex::decode(hex_digits)
+            .map_err(|err| ParseError(<serde_json::Error as serde::de::Error>::custom(err)))?;
+
+        Ok((bytes, &input[consumed..]))
+    }
+}
+
+impl<'a> Iterator for GenesisStorageEntries<'a> {
+    type Item = Result<(HexString, HexString), ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let input = self.remaining.trim_start();
+
+        let input = if self.first {
+            if input.is_empty() {
+                return None;
+            }
+            input
+        } else {
+            match input.strip_prefix(',') {
+                None if input.is_empty() => return None,
+                None => {
+                    self.remaining = "";
+                    return Some(Err(ParseError(<serde_json::Error as serde::de::Error>::custom(
+                        "expected ',' between genesis storage entries",
+                    ))));
+                }
+                Some(rest) => {
+                    let rest = rest.trim_start();
+                    if rest.is_empty() {
+                        // A `,` immediately followed by the end of the object is a trailing
+                        // comma, which isn't valid JSON.
+                        self.remaining = "";
+                        return Some(Err(ParseError(
+                            <serde_json::Error as serde::de::Error>::custom(
+                                "trailing ',' in genesis storage object",
+                            ),
+                        )));
+                    }
+                    rest
+                }
+            }
+        };
+
+        let result = (|| {
+            let (key, after_key) = Self::decode_hex_json_string(input)?;
+            let after_colon = after_key.trim_start().strip_prefix(':').ok_or_else(|| {
+                ParseError(<serde_json::Error as serde::de::Error>::custom(
+                    "expected ':' after a genesis storage key",
+                ))
+            })?;
+            let (value, rest) = Self::decode_hex_json_string(after_colon.trim_start())?;
+            Ok((HexString(key), HexString(value), rest))
+        })();
+
+        match result {
+            Ok((key, value, rest)) => {
+                self.first = false;
+                self.remaining = rest;
+                Some(Ok((key, value)))
+            }
+            Err(err) => {
+                // Make sure that a decoding error is reported only once, instead of looping
+                // forever on the same invalid input.
+                self.remaining = "";
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hashbrown::HashSet;
+
+    /// Builds a minimal [`ChainSpec`] with an empty raw genesis, for use by tests that only
+    /// care about a handful of fields.
+    fn test_chain_spec(
+        bad_blocks: Option<HashSet<HashHexString, fnv::FnvBuildHasher>>,
+        fork_blocks: Option<Vec<(u64, HashHexString)>>,
+    ) -> ChainSpec {
+        ChainSpec {
+            client_spec: structs::ClientSpec {
+                name: "test".into(),
+                id: "test".into(),
+                chain_type: ChainType::Development,
+                code_substitutes: HashMap::default(),
+                boot_nodes: Vec::new(),
+                telemetry_endpoints: None,
+                protocol_id: None,
+                fork_id: None,
+                properties: None,
+                fork_blocks,
+                bad_blocks,
+                consensus_engine: (),
+                genesis: structs::Genesis::Raw(structs::RawGenesis {
+                    top: BTreeMap::new(),
+                    children_default: BTreeMap::new(),
+                }),
+                light_sync_state: None,
+                parachain: None,
+            },
+        }
+    }
+
+    #[test]
+    fn check_block_is_allowed_rejects_blacklisted_block() {
+        let mut bad_blocks = HashSet::with_hasher(fnv::FnvBuildHasher::default());
+        bad_blocks.insert(HashHexString([1; 32]));
+        let spec = test_chain_spec(Some(bad_blocks), None);
+
+        assert_eq!(
+            spec.check_block_is_allowed(5, &[1; 32]),
+            Err(BadBlockError::Blacklisted)
+        );
+        assert_eq!(spec.check_block_is_allowed(5, &[2; 32]), Ok(()));
+    }
+
+    #[test]
+    fn check_block_is_allowed_rejects_fork_mismatch() {
+        let spec = test_chain_spec(None, Some(vec![(10, HashHexString([3; 32]))]));
+
+        assert_eq!(
+            spec.check_block_is_allowed(10, &[4; 32]),
+            Err(BadBlockError::ForkMismatch)
+        );
+        assert_eq!(spec.check_block_is_allowed(10, &[3; 32]), Ok(()));
+        assert_eq!(spec.check_block_is_allowed(11, &[4; 32]), Ok(()));
+    }
+
+    #[test]
+    fn check_block_is_allowed_accepts_everything_by_default() {
+        let spec = test_chain_spec(None, None);
+        assert_eq!(spec.check_block_is_allowed(0, &[0; 32]), Ok(()));
+    }
+
+    #[test]
+    fn builder_round_trips_through_json() {
+        let spec = ChainSpecBuilder::new("My Chain", "my-chain")
+            .chain_type(ChainType::Local)
+            .boot_node("/ip4/127.0.0.1/tcp/30333")
+            .protocol_id("my-protocol")
+            .insert_top_storage(vec![1, 2], vec![3, 4])
+            .insert_child_default_storage(vec![5, 6], vec![7], 0)
+            .build();
+
+        let reparsed = ChainSpec::from_json_bytes(spec.as_json_bytes().as_bytes()).unwrap();
+
+        assert_eq!(reparsed.name(), "My Chain");
+        assert_eq!(reparsed.id(), "my-chain");
+        assert_eq!(reparsed.protocol_id(), Some("my-protocol"));
+        assert_eq!(
+            reparsed.boot_nodes().collect::<Vec<_>>(),
+            vec!["/ip4/127.0.0.1/tcp/30333"]
+        );
+    }
+
+    #[test]
+    fn genesis_state_root_hash_takes_precedence_over_buffered_raw_storage() {
+        let spec = ChainSpecBuilder::new("My Chain", "my-chain")
+            .insert_top_storage(vec![1], vec![2])
+            .genesis_state_root_hash([0x42; 32])
+            .build();
+
+        match &spec.client_spec.genesis {
+            structs::Genesis::StateRootHash(hash) => assert_eq!(hash.0, [0x42; 32]),
+            structs::Genesis::Raw(_) => panic!("expected a state root hash genesis"),
+        }
+    }
+
+    /// Wraps `top` (assumed to already be a valid JSON object, e.g. `{"0x01":"0x02"}`) into a
+    /// minimal chain specification document.
+    fn spec_json_with_raw_top(top: &str) -> Vec<u8> {
+        format!(
+            r#"{{"name":"n","id":"i","bootNodes":[],"telemetryEndpoints":null,"protocolId":null,"properties":null,"forkBlocks":null,"badBlocks":null,"genesis":{{"raw":{{"top":{},"childrenDefault":{{}}}}}},"lightSyncState":null}}"#,
+            top
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn genesis_storage_entries_decodes_in_document_order_without_deduping() {
+        let json = spec_json_with_raw_top(r#"{"0x02":"0xbb","0x01":"0xaa","0x01":"0xcc"}"#);
+        let entries = ChainSpec::genesis_storage_top_trie_entries_from_json_bytes(&json)
+            .unwrap()
+            .unwrap();
+
+        let decoded: Vec<_> = entries.map(Result::unwrap).collect();
+        assert_eq!(
+            decoded,
+            vec![
+                (HexString(vec![0x02]), HexString(vec![0xbb])),
+                (HexString(vec![0x01]), HexString(vec![0xaa])),
+                (HexString(vec![0x01]), HexString(vec![0xcc])),
+            ]
+        );
+    }
+
+    #[test]
+    fn genesis_storage_trie_root_sorts_and_dedupes_like_a_btreemap() {
+        let json = spec_json_with_raw_top(r#"{"0x02":"0xbb","0x01":"0xaa","0x01":"0xcc"}"#);
+        let entries = ChainSpec::genesis_storage_top_trie_entries_from_json_bytes(&json)
+            .unwrap()
+            .unwrap();
+
+        let sorted = sorted_deduped_entries(entries).unwrap();
+
+        let mut expected = BTreeMap::new();
+        expected.insert(vec![0x01], vec![0xcc]);
+        expected.insert(vec![0x02], vec![0xbb]);
+        assert_eq!(sorted, expected);
+    }
+
+    #[test]
+    fn genesis_storage_entries_rejects_trailing_comma() {
+        // `serde_json` already rejects this object when parsing a whole document, so the
+        // iterator is built directly from the object's text.
+        let entries = GenesisStorageEntries::new(r#"{"0x01":"0xaa",}"#).unwrap();
+
+        let results: Vec<_> = entries.collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+    }
+
+    #[test]
+    fn genesis_storage_entries_empty_object_yields_nothing() {
+        let json = spec_json_with_raw_top("{}");
+        let entries = ChainSpec::genesis_storage_top_trie_entries_from_json_bytes(&json)
+            .unwrap()
+            .unwrap();
+        assert_eq!(entries.count(), 0);
+    }
+}
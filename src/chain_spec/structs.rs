@@ -48,7 +48,6 @@ pub(super) struct ClientSpec {
     ///
     /// See also <https://github.com/paritytech/substrate/pull/8898>.
     #[serde(default)]
-    // TODO: make use of this
     pub(super) code_substitutes: HashMap<NumberAsString, HexString, fnv::FnvBuildHasher>,
     pub(super) boot_nodes: Vec<String>,
     pub(super) telemetry_endpoints: Option<Vec<(String, u8)>>,
@@ -56,9 +55,10 @@ pub(super) struct ClientSpec {
     #[serde(default = "Default::default", skip_serializing_if = "Option::is_none")]
     pub(super) fork_id: Option<String>,
     pub(super) properties: Option<Box<serde_json::value::RawValue>>,
-    // TODO: make use of this
+    /// List of `(height, hash)` tuples. The block at the given height on the chain must have
+    /// the given hash, or must be rejected.
     pub(super) fork_blocks: Option<Vec<(u64, HashHexString)>>,
-    // TODO: make use of this
+    /// List of block hashes that must unconditionally be rejected.
     pub(super) bad_blocks: Option<HashSet<HashHexString, FnvBuildHasher>>,
     // Unused but for some reason still part of the chain specs.
     #[serde(default, skip_serializing)]
@@ -163,9 +163,29 @@ impl<'a> serde::Deserialize<'a> for NumberAsString {
         let string = String::deserialize(deserializer)?;
 
         if let Some(hex) = string.strip_prefix("0x") {
-            // TODO: the hexadecimal format support is just a complete hack during a transition period for https://github.com/paritytech/substrate/pull/10600 ; must be removed before we actually make use of the code substitutes
-            let _bytes = hex::decode(&hex).map_err(serde::de::Error::custom)?;
-            Ok(NumberAsString(0))
+            // An odd number of nibbles isn't valid hexadecimal, but is nonetheless accepted by
+            // typed JSON uint parsers by left-padding it with a zero.
+            let padded;
+            let hex = if hex.len() % 2 != 0 {
+                padded = format!("0{}", hex);
+                &padded[..]
+            } else {
+                hex
+            };
+
+            let bytes = hex::decode(hex).map_err(serde::de::Error::custom)?;
+            if bytes.len() > 8 {
+                return Err(serde::de::Error::custom(
+                    "block number doesn't fit in a u64",
+                ));
+            }
+
+            let mut value = 0u64;
+            for byte in bytes {
+                value = (value << 8) | u64::from(byte);
+            }
+
+            Ok(NumberAsString(value))
         } else if let Ok(num) = string.parse() {
             Ok(NumberAsString(num))
         } else {
@@ -220,3 +240,41 @@ impl<'a> serde::Deserialize<'a> for HashHexString {
         Ok(HashHexString(out))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::NumberAsString;
+
+    #[test]
+    fn number_as_string_decimal() {
+        let parsed: NumberAsString = serde_json::from_str("\"1234\"").unwrap();
+        assert_eq!(parsed.0, 1234);
+    }
+
+    #[test]
+    fn number_as_string_hex_even_number_of_nibbles() {
+        let parsed: NumberAsString = serde_json::from_str("\"0x00ff\"").unwrap();
+        assert_eq!(parsed.0, 0xff);
+    }
+
+    #[test]
+    fn number_as_string_hex_odd_number_of_nibbles_is_left_padded() {
+        let parsed: NumberAsString = serde_json::from_str("\"0xfff\"").unwrap();
+        assert_eq!(parsed.0, 0xfff);
+
+        let parsed: NumberAsString = serde_json::from_str("\"0x1\"").unwrap();
+        assert_eq!(parsed.0, 1);
+    }
+
+    #[test]
+    fn number_as_string_hex_more_than_8_bytes_is_rejected() {
+        let result = serde_json::from_str::<NumberAsString>("\"0x010000000000000000\"");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn number_as_string_neither_hex_nor_decimal_is_rejected() {
+        let result = serde_json::from_str::<NumberAsString>("\"not a number\"");
+        assert!(result.is_err());
+    }
+}